@@ -1,22 +1,91 @@
 use crate::{error::*, *};
 use chrono::{DateTime, NaiveDateTime, Utc};
+use futures::Stream;
 use hex::ToHex;
 use lazy_static::lazy_static;
-use regex::{bytes, Regex};
+use rand::Rng;
+use regex::Regex;
 use reqwest::{self, header};
-use serde_json::Value;
+use rmpv::Value;
+use serde::Deserialize;
 use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
 use std::str;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
 
 const DEFAULT_UTILS_BASE_URL: &str =
     "https://ggst-utils-default-rtdb.europe-west1.firebasedatabase.app";
 const DEFAULT_BASE_URL: &str = "https://ggst-game.guiltygear.com";
 
-/// Context struct which contains the base urls used for api requests. Use the associated methods
-/// to overwrite urls if necessary.
+// Backoff parameters for the retry loop in `send_with_retry`. The delay before attempt `n` is
+// `RETRY_BASE` doubled `n` times, capped at `RETRY_MAX`, plus up to 25% random jitter.
+const RETRY_BASE: Duration = Duration::from_millis(200);
+const RETRY_MAX: Duration = Duration::from_secs(10);
+
+/// A simple token-bucket rate limiter shared across all requests made with a `Context`.
+///
+/// `tokens` is refilled lazily on every `acquire` call based on how much time has passed since
+/// `last_refill`, rather than on a background timer, so an idle `Context` doesn't need a running
+/// task to "catch up".
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, refill_per_sec: f64) -> Self {
+        TokenBucket {
+            capacity,
+            tokens: capacity,
+            refill_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Block until a token is available, then take one.
+    async fn acquire(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens < 1.0 {
+            let wait_secs = (1.0 - self.tokens) / self.refill_per_sec;
+            tokio::time::sleep(Duration::from_secs_f64(wait_secs)).await;
+            self.tokens = 1.0;
+            self.last_refill = Instant::now();
+        }
+
+        self.tokens -= 1.0;
+    }
+}
+
+/// Context struct which contains the base urls used for api requests, as well as the pooled
+/// HTTP client all requests are sent through. Use the associated methods to overwrite urls or
+/// client behaviour if necessary.
+///
+/// A single `reqwest::Client` is built once and reused for every call made with this `Context`,
+/// instead of every request opening a fresh connection. The client is cheap to clone (it's backed
+/// by an `Arc` internally), so a `Context` can be shared across tasks by cloning it.
+///
+/// `Context` can also throttle and retry the requests it sends, see [`Context::rate_limit`] and
+/// [`Context::max_retries`].
+///
+/// `Context` does not choose a TLS backend itself: the pooled client is built with
+/// `reqwest::Client::builder()` and uses whichever TLS backend `reqwest` was compiled with.
+#[derive(Clone)]
 pub struct Context {
     base_url: String,
     utils_base_url: String,
+    client: reqwest::Client,
+    request_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    rate_limiter: Option<Arc<Mutex<TokenBucket>>>,
+    max_retries: usize,
 }
 
 impl Default for Context {
@@ -24,6 +93,11 @@ impl Default for Context {
         Context {
             base_url: DEFAULT_BASE_URL.to_string(),
             utils_base_url: DEFAULT_UTILS_BASE_URL.to_string(),
+            client: reqwest::Client::new(),
+            request_timeout: None,
+            connect_timeout: None,
+            rate_limiter: None,
+            max_retries: 0,
         }
     }
 }
@@ -45,11 +119,142 @@ impl Context {
         self.utils_base_url = utils_base_url;
         self
     }
+
+    /// Bound the total time (connect + send + receive) a single request may take before it is
+    /// considered failed. Rebuilds the pooled client so the new timeout applies to every request
+    /// made from here on. There is no timeout by default, matching `reqwest`'s own default.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self.rebuild_client()
+    }
+
+    /// Bound how long establishing the initial connection may take, independent of the overall
+    /// request timeout. Rebuilds the pooled client so the new timeout applies to every request
+    /// made from here on.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self.rebuild_client()
+    }
+
+    fn rebuild_client(mut self) -> Self {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        self.client = builder.build().expect("Failed to build reqwest client");
+        self
+    }
+
+    /// Throttle outgoing requests to at most `capacity` in a burst, refilling at `refill_per_sec`
+    /// tokens per second afterwards. Disabled by default. Heavy scrapers polling `get_replays` in
+    /// a loop should set this so they don't hammer the GGST servers, e.g.
+    /// `Context::new().rate_limit(5.0, 1.0)` allows a burst of 5 requests, then roughly 1 per
+    /// second.
+    ///
+    /// # Panics
+    /// Panics if `capacity` or `refill_per_sec` is not a positive, finite number. A zero or
+    /// negative refill rate would never replenish the bucket, which would otherwise only surface
+    /// later as a hang (or worse, a panic converting an infinite wait into a `Duration`) the
+    /// first time a request exhausts the burst.
+    pub fn rate_limit(mut self, capacity: f64, refill_per_sec: f64) -> Self {
+        assert!(
+            capacity.is_finite() && capacity > 0.0,
+            "rate_limit: capacity must be a positive, finite number, got {}",
+            capacity
+        );
+        assert!(
+            refill_per_sec.is_finite() && refill_per_sec > 0.0,
+            "rate_limit: refill_per_sec must be a positive, finite number, got {}",
+            refill_per_sec
+        );
+        self.rate_limiter = Some(Arc::new(Mutex::new(TokenBucket::new(
+            capacity,
+            refill_per_sec,
+        ))));
+        self
+    }
+
+    /// Retry a request up to `max_retries` times, with exponential backoff, if it times out,
+    /// fails to connect, or the server responds with `429 Too Many Requests` or a `5xx` status.
+    /// Defaults to `0`, i.e. no retries.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
 }
 
+/// Send `request`, applying the `Context`'s rate limit (if any) beforehand and retrying (if
+/// configured) on timeouts, connection errors, `429`, and `5xx` responses. A `Retry-After` header
+/// on a `429`/`5xx` response takes precedence over the computed backoff delay.
+async fn send_with_retry(
+    context: &Context,
+    request: reqwest::RequestBuilder,
+) -> Result<reqwest::Response> {
+    if let Some(limiter) = &context.rate_limiter {
+        limiter.lock().await.acquire().await;
+    }
+
+    let mut attempt = 0;
+    loop {
+        let this_attempt = request.try_clone().ok_or_else(|| {
+            Error::UnexpectedResponse("Could not clone request body for retry".into())
+        })?;
+
+        match this_attempt.send().await {
+            Ok(response) => {
+                let status = response.status();
+                let retryable = status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+                if retryable && attempt < context.max_retries {
+                    let delay = retry_after(&response).unwrap_or_else(|| backoff_delay(attempt));
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+                return Ok(response);
+            }
+            Err(err) => {
+                if (err.is_timeout() || err.is_connect()) && attempt < context.max_retries {
+                    tokio::time::sleep(backoff_delay(attempt)).await;
+                    attempt += 1;
+                    continue;
+                }
+                return Err(err.into());
+            }
+        }
+    }
+}
+
+/// Parse a `Retry-After` header expressed as a number of seconds, if present.
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Exponential backoff for retry attempt `attempt` (0-indexed), capped at `RETRY_MAX` with up to
+/// 25% random jitter added to avoid synchronized retries across concurrent callers.
+fn backoff_delay(attempt: usize) -> Duration {
+    let exp = RETRY_BASE
+        .saturating_mul(1 << attempt.min(16))
+        .min(RETRY_MAX);
+    let jitter = rand::thread_rng().gen_range(0..=exp.as_millis() as u64 / 4);
+    exp + Duration::from_millis(jitter)
+}
+
+// Highest page count `get_replays` (and, transitively, `replay_stream`'s backfill) will accept
+// in one call.
+const MAX_REPLAY_PAGES: usize = 100;
+
 /// Retrieve the latest set of replays. Each page contains approximately 10 replays, however this is not
 /// guaranteed. Indicate the min and maximum floor you want to query.
-/// No more than 100 pages can be queried at a time. If no matches can be found the parsing will
+/// No more than [`MAX_REPLAY_PAGES`] pages can be queried at a time. A page with no matches ends the query early
+/// and returns what was found so far (possibly nothing); only a malformed page makes the parsing
 /// fail. Usually a few replays have weird timestamps from the future. It is recommended to apply a
 /// filter on the current time before using any matches, like `.filter(|m| m.timestamp() <
 /// &chrono::Utc::now())`
@@ -60,10 +265,10 @@ pub async fn get_replays(
     max_floor: Floor,
 ) -> Result<impl Iterator<Item = Match>> {
     // Check for invalid inputs
-    if pages > 100 {
+    if pages > MAX_REPLAY_PAGES {
         return Err(Error::InvalidArguments(format!(
-            "pages: {} Cannot query more than 100 pages",
-            pages
+            "pages: {} Cannot query more than {} pages",
+            pages, MAX_REPLAY_PAGES
         )));
     }
     if min_floor > max_floor {
@@ -74,35 +279,26 @@ pub async fn get_replays(
     }
 
     let request_url = format!("{}/api/catalog/get_replay", context.base_url);
-    let client = reqwest::Client::new();
 
     // Assume at most 10 replays per page for pre allocation
     let mut matches = BTreeSet::new();
     for i in 0..pages {
-        // Construct the query string
-        let hex_index = format!("{:02X}", i);
+        // Construct the query string. Only the page index and floor range actually vary between
+        // requests, so only that part is built through a real msgpack encoder; the rest of the
+        // envelope (device id, session token, client version) is a fixed, opaque value.
+        let page_segment = encode_replay_page_segment(i as u8, min_floor, max_floor)?;
         let query_string = format!(
-            "9295B2323131303237313133313233303038333834AD3631613565643466343631633202A5302E302E38039401CC{}0A9AFF00{}{}90FFFF000001",
-            hex_index,
-            min_floor.to_hex(),
-            max_floor.to_hex());
-        let response = client
+            "{}{}",
+            REPLAY_QUERY_PREFIX,
+            page_segment.encode_hex_upper::<String>()
+        );
+        let request = context
+            .client
             .post(&request_url)
             .header(header::USER_AGENT, "Steam")
             .header(header::CACHE_CONTROL, "no-cache")
-            .form(&[("data", query_string)])
-            .send()
-            .await?;
-
-        // Regex's to parse the raw bytes received
-        lazy_static! {
-            // This separates the matches from each other
-            static ref MATCH_SEP: bytes::Regex =
-                bytes::Regex::new(r"(?-u)\x01\x00\x00\x00")
-                    .expect("Could not compile regex");
-            // The separator which separates data within a match segment
-            static ref PLAYER_DATA_START: bytes::Regex = bytes::Regex::new(r"(?-u)\x95\xb2").expect("Could not compile regex");
-        }
+            .form(&[("data", query_string)]);
+        let response = send_with_retry(context, request).await?;
 
         // Convert the response to raw bytes
         let bytes = response.bytes().await?;
@@ -114,221 +310,290 @@ pub async fn get_replays(
             return Ok(matches.into_iter());
         }
 
-        // Remove the first 61 bytes, they are static header, we don't need them
+        // Remove the first 61 bytes, they are a static header outside the msgpack payload itself
         let bytes = bytes.slice(61..);
 
-        // Split on the match separator and keep non empty results only
-        // This should give us 10 separate matches
-        for raw_match in MATCH_SEP.split(&bytes).filter(|b| !b.is_empty()) {
-            // Structure of the data to be extracted:
-            // We have three sections that have to be parsed
-            // Section 1: {floor}{p1_char}{p2_char}
-            // Section 2: \x95\xb2{p1_id [18 chars]}\xa_{p1_name}\xb1{p1_some_number}\xaf{p1_online_id}\x07
-            // Section 3: \x95\xb2{p2_id}\xa_{p2_name}\xb1{p2_some_number}\xaf{p2_online_id}\t{winner}\xb3{timestamp}
-
-            // Split the match data on the player separator
-            let mut data = PLAYER_DATA_START
-                .split(raw_match)
-                .collect::<Vec<_>>()
-                .into_iter()
-                .rev()
-                .take(3)
-                .rev();
-
-            // Section 1
-            let (floor, p1_char, p2_char) = match data.next() {
-                Some(b) => {
-                    let n = b.len();
-                    if n < 3 {
-                        return Err(Error::UnexpectedResponse(
-                            "First data part does not have 3 bytes".into(),
-                        ));
-                    }
-                    (b[n - 3], b[n - 2], b[n - 1])
-                }
-                None => {
-                    return Err(Error::UnexpectedResponse(
-                        "Could not find first data part of response".into(),
-                    ))
-                }
-            };
+        // The remaining bytes are a sequence of per-match msgpack records glued together with a
+        // literal `\x01\x00\x00\x00` separator. Split on that (plain byte search, not a regex)
+        // and decode each record as real msgpack instead of picking it apart by hand.
+        for record_bytes in split_on(&bytes, MATCH_SEPARATOR)
+            .into_iter()
+            .filter(|b| !b.is_empty())
+        {
+            let mut cursor = record_bytes;
+            let record = rmpv::decode::read_value(&mut cursor).map_err(|e| {
+                Error::UnexpectedResponse(format!("Could not decode replay record: {}", e))
+            })?;
+            matches.insert(parse_replay_record(&record)?);
+        }
+    }
+    Ok(matches.into_iter())
+}
 
-            // Section 2
-            let (p1_id, p1_name) = match data.next() {
-                Some(b) => {
-                    // We check if the array is long enough
-                    // it has to be at least 18 characters for the player user_id
-                    // one character for the separator \xa_ and then at least 1 byte for
-                    // the username
-                    if b.len() < 20 {
-                        return Err(Error::UnexpectedResponse(format!(
-                            "Second data part does not have 20 bytes, has {} instead: {} in {}",
-                            b.len(),
-                            show_buf(b),
-                            show_buf(raw_match)
-                        )));
-                    }
+// Fixed msgpack envelope the client always sends when requesting a page of replays: a device id
+// (fixstr, 18 bytes), an opaque session token (fixstr, 13 bytes), the client version (fixstr, "0.0.8"),
+// and a leading field count, all of which are constant. Only the tail built by
+// `encode_replay_page_segment` (the page index and floor range) changes per request.
+const REPLAY_QUERY_PREFIX: &str =
+    "9295B2323131303237313133313233303038333834AD3631613565643466343631633202A5302E302E3803";
+
+// Literal byte sequence the server glues per-match msgpack records together with.
+const MATCH_SEPARATOR: &[u8] = b"\x01\x00\x00\x00";
+
+/// Encode the part of a `get_replay` request that actually varies: `[1, page, 10, [-1, 0,
+/// min_floor, max_floor, [], -1, -1, 0, 0, 1]]` as real msgpack, rather than formatting each byte
+/// as a hex literal by hand.
+fn encode_replay_page_segment(page: u8, min_floor: Floor, max_floor: Floor) -> Result<Vec<u8>> {
+    use rmp::encode::{write_array_len, write_sint, write_u8, write_uint};
+
+    let encode_err =
+        |e: rmp::encode::ValueWriteError| Error::UnexpectedResponse(format!("Could not encode replay request: {}", e));
+
+    let mut buf = Vec::new();
+    write_array_len(&mut buf, 4).map_err(encode_err)?;
+    write_uint(&mut buf, 1).map_err(encode_err)?;
+    write_u8(&mut buf, page).map_err(encode_err)?;
+    write_uint(&mut buf, 10).map_err(encode_err)?;
+    write_array_len(&mut buf, 10).map_err(encode_err)?;
+    write_sint(&mut buf, -1).map_err(encode_err)?;
+    write_uint(&mut buf, 0).map_err(encode_err)?;
+    write_uint(&mut buf, min_floor as u8 as u64).map_err(encode_err)?;
+    write_uint(&mut buf, max_floor as u8 as u64).map_err(encode_err)?;
+    write_array_len(&mut buf, 0).map_err(encode_err)?;
+    write_sint(&mut buf, -1).map_err(encode_err)?;
+    write_sint(&mut buf, -1).map_err(encode_err)?;
+    write_uint(&mut buf, 0).map_err(encode_err)?;
+    write_uint(&mut buf, 0).map_err(encode_err)?;
+    write_uint(&mut buf, 1).map_err(encode_err)?;
+    Ok(buf)
+}
 
-                    let name = match b[19..].split(|f| *f == b'\xb1').next() {
-                        Some(name_bytes) => String::from_utf8_lossy(name_bytes),
-                        None => {
-                            return Err(Error::UnexpectedResponse(format!(
-                                "Could not parse player1 name: {}",
-                                show_buf(&b[19..])
-                            )))
-                        }
-                    };
-                    (String::from_utf8_lossy(&b[0..18]), name)
-                }
-                None => {
-                    return Err(Error::UnexpectedResponse(
-                        "Could not find second data part of response".into(),
-                    ))
-                }
-            };
+/// Split `haystack` on every non-overlapping occurrence of `separator`. Like `[T]::split`, but
+/// for a multi-byte needle instead of a single byte or predicate.
+fn split_on<'a>(haystack: &'a [u8], separator: &[u8]) -> Vec<&'a [u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = haystack;
+    while let Some(pos) = rest
+        .windows(separator.len())
+        .position(|window| window == separator)
+    {
+        chunks.push(&rest[..pos]);
+        rest = &rest[pos + separator.len()..];
+    }
+    chunks.push(rest);
+    chunks
+}
 
-            // Section 3
-            let (p2_id, p2_name, winner, time) = match data.next() {
-                Some(b) => {
-                    // We check if the array is long enough, 76 characters required for a 1 byte
-                    // username, it has to be at least 76 characters for the player user_id, online_id,
-                    // timestamp, the other number and the winner indicator and separators
-                    // and then at least 1 byte for the username
-                    // There do exist weird edge cases where the third data part does not contain
-                    // an online id, instead it has a dummy user name, this will then take 71 bytes
-                    // instead
-                    if b.len() < 71 {
-                        return Err(Error::UnexpectedResponse(format!(
-                            "Third data part does not have 71 bytes, has {} instead: {} in {}",
-                            b.len(),
-                            show_buf(b),
-                            show_buf(raw_match)
-                        )));
-                    }
+/// Build a `Match` out of one decoded replay record: a 3 element array `[header, player1,
+/// player2_and_result]`. `header`'s last three entries are `[floor, p1_char, p2_char]`, and
+/// `player1`/`player2_and_result` both have `[id, name, ...]` as their first two entries.
+/// `player2_and_result` additionally has `[..., winner, timestamp]` as its *last* two entries;
+/// reading those from the end rather than counting forward from the name is what makes this
+/// robust to the online-id-absent edge case, where the record simply has one fewer field in the
+/// middle.
+fn parse_replay_record(record: &Value) -> Result<Match> {
+    let fields = value_as_array(record, "replay record")?;
+    if fields.len() < 3 {
+        return Err(Error::UnexpectedResponse(format!(
+            "Replay record has {} fields, expected at least 3",
+            fields.len()
+        )));
+    }
 
-                    let name = match b[19..].split(|f| *f == b'\xb1').next() {
-                        Some(name_bytes) => String::from_utf8_lossy(name_bytes),
-                        None => {
-                            return Err(Error::UnexpectedResponse(format!(
-                                "Could not parse player2 name: {}",
-                                show_buf(&b[19..])
-                            )))
-                        }
-                    };
-
-                    // first 38 bytes are unnecessary as they contain the username and id's
-                    // \xb3 is in front of the timestamp, so we split the bytes on that and take
-                    // the last two segements, which should be the winner and timestamp
-                    // This can break if there are more bytes behind the timestamp that contain the
-                    // \xb3 byte
-                    let winner_time_bytes = b[38..]
-                        .split(|f| *f == b'\xb3')
-                        .rev()
-                        .take(2)
-                        .collect::<Vec<_>>();
-                    let time = match winner_time_bytes.get(0) {
-                        Some(bytes) => {
-                            // 16 bytes before the relevant section
-                            // We need 1 byte for the winner, 1 byte for the separator and 19 bytes
-                            // for the timestamp
-                            if bytes.len() < 19 {
-                                return Err(Error::UnexpectedResponse(format!(
-                                    "Not enough bytes to parse timestamp: {}",
-                                    show_buf(&b[38..])
-                                )));
-                            }
-                            String::from_utf8_lossy(&bytes[0..19])
-                        }
-                        None => {
-                            return Err(Error::UnexpectedResponse(format!(
-                                "Could not split bytes to parse winner and timestamp: {}",
-                                show_buf(&b[38..])
-                            )))
-                        }
-                    };
-                    let winner = match winner_time_bytes.get(1) {
-                        Some(bytes) => match bytes.last() {
-                            None => {
-                                return Err(Error::UnexpectedResponse(format!(
-                                    "Could not find winner in bytes: {}",
-                                    show_buf(&b[38..])
-                                )))
-                            }
-                            Some(b) => b,
-                        },
-                        None => {
-                            return Err(Error::UnexpectedResponse(format!(
-                                "Could not split bytes to parse winner: {}",
-                                show_buf(&b[38..])
-                            )))
-                        }
-                    };
-                    (String::from_utf8_lossy(&b[0..18]), name, winner, time)
-                }
-                None => {
-                    return Err(Error::UnexpectedResponse(
-                        "Could not find third data part of match".into(),
-                    ))
+    let header = value_as_array(&fields[0], "replay record field 0 (header)")?;
+    if header.len() < 3 {
+        return Err(Error::UnexpectedResponse(
+            "Replay header does not have the 3 expected fields (floor, p1_char, p2_char)".into(),
+        ));
+    }
+    let n = header.len();
+    let floor = value_as_u8(&header[n - 3], "header floor")?;
+    let p1_char = value_as_u8(&header[n - 2], "header p1_char")?;
+    let p2_char = value_as_u8(&header[n - 1], "header p2_char")?;
+
+    let (p1_id, p1_name) = parse_player_identity(&fields[1], "player 1")?;
+
+    let p2_fields = value_as_array(&fields[2], "replay record field 2 (player 2 + result)")?;
+    if p2_fields.len() < 4 {
+        return Err(Error::UnexpectedResponse(format!(
+            "player 2 + result has {} fields, expected at least 4 (id, name, ..., winner, timestamp)",
+            p2_fields.len()
+        )));
+    }
+    let p2_id = p2_fields[0]
+        .as_str()
+        .ok_or_else(|| Error::UnexpectedResponse("Could not read player 2 id as a string".into()))?;
+    let p2_name = p2_fields[1].as_str().ok_or_else(|| {
+        Error::UnexpectedResponse("Could not read player 2 name as a string".into())
+    })?;
+    let m = p2_fields.len();
+    let winner = value_as_u8(&p2_fields[m - 2], "winner")?;
+    let timestamp = p2_fields[m - 1].as_str().ok_or_else(|| {
+        Error::UnexpectedResponse("Could not read match timestamp as a string".into())
+    })?;
+
+    Ok(Match {
+        floor: Floor::from_u8(floor)?,
+        timestamp: match NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S") {
+            Ok(t) => DateTime::<Utc>::from_utc(t, Utc),
+            Err(_) => {
+                return Err(Error::UnexpectedResponse(format!(
+                    "Could not parse datetime {}",
+                    timestamp
+                )))
+            }
+        },
+        players: (
+            Player {
+                id: p1_id.parse().map_err(|_| {
+                    Error::UnexpectedResponse(format!("Could not parse u64 id from {}", p1_id))
+                })?,
+                name: p1_name.to_string(),
+                character: Character::from_u8(p1_char)?,
+            },
+            Player {
+                id: p2_id.parse().map_err(|_| {
+                    Error::UnexpectedResponse(format!("Could not parse u64 id from {}", p2_id))
+                })?,
+                name: p2_name.to_string(),
+                character: Character::from_u8(p2_char)?,
+            },
+        ),
+        winner: match winner {
+            1 => Winner::Player1,
+            2 => Winner::Player2,
+            _ => {
+                return Err(Error::UnexpectedResponse(format!(
+                    "Could not parse winner {}",
+                    winner
+                )))
+            }
+        },
+    })
+}
+
+/// Read a player's `[id, name, ...]` record, returning its first two fields.
+fn parse_player_identity<'a>(value: &'a Value, label: &str) -> Result<(&'a str, &'a str)> {
+    let fields = value_as_array(value, label)?;
+    if fields.len() < 2 {
+        return Err(Error::UnexpectedResponse(format!(
+            "{} has {} fields, expected at least 2 (id, name)",
+            label,
+            fields.len()
+        )));
+    }
+    let id = fields[0].as_str().ok_or_else(|| {
+        Error::UnexpectedResponse(format!("Could not read {} id as a string", label))
+    })?;
+    let name = fields[1].as_str().ok_or_else(|| {
+        Error::UnexpectedResponse(format!("Could not read {} name as a string", label))
+    })?;
+    Ok((id, name))
+}
+
+fn value_as_array<'a>(value: &'a Value, label: &str) -> Result<&'a Vec<Value>> {
+    value
+        .as_array()
+        .ok_or_else(|| Error::UnexpectedResponse(format!("{} was not a msgpack array", label)))
+}
+
+fn value_as_u8(value: &Value, label: &str) -> Result<u8> {
+    let v = value.as_u64().ok_or_else(|| {
+        Error::UnexpectedResponse(format!("Could not read {} as an integer", label))
+    })?;
+    u8::try_from(v)
+        .map_err(|_| Error::UnexpectedResponse(format!("{} value {} out of range for a byte", label, v)))
+}
+
+/// Controls what [`replay_stream`] does on its very first tick.
+pub enum StreamStart {
+    /// Fetch `pages` pages immediately and emit whatever matches they contain, then switch to
+    /// polling for new ones. Useful to backfill some recent history before going live. Clamped to
+    /// [`MAX_REPLAY_PAGES`] rather than failing the stream on an oversized value.
+    Backfill(usize),
+    /// Fetch the usual per-tick pages to seed the seen-set, but don't emit anything from them.
+    /// Only matches found on later ticks are emitted.
+    SkipExisting,
+}
+
+// How many pages to poll per tick once past the first one. Matches are almost always found on
+// the first page or two, so polling more than this per tick would just waste requests.
+const STREAM_POLL_PAGES: usize = 2;
+
+// How many matches `replay_stream` remembers, to bound its memory use on a long-running stream.
+const STREAM_SEEN_CAPACITY: usize = 2_000;
+
+/// Subscribe to newly played matches as they appear, instead of manually polling [`get_replays`].
+///
+/// Every `interval`, this polls the first couple of pages of replays in `[min_floor, max_floor]`
+/// and yields any match not already seen, oldest first, before inserting it into a bounded
+/// seen-set (capped at [`STREAM_SEEN_CAPACITY`] entries) so it isn't yielded again. `filter` is
+/// applied before a match is checked against the seen-set or emitted; pass something like `|m|
+/// m.timestamp() < &chrono::Utc::now()` to drop the future-timestamped anomalies noted on
+/// [`get_replays`].
+///
+/// `start` controls the first tick: [`StreamStart::Backfill`] emits `n` pages worth of matches
+/// right away (clamped to [`MAX_REPLAY_PAGES`], the limit [`get_replays`] itself enforces), while
+/// [`StreamStart::SkipExisting`] seeds the seen-set from the first poll without emitting anything,
+/// so only matches played after the stream started show up.
+///
+/// Transient errors from a single poll (e.g. a malformed response) are swallowed and retried on
+/// the next tick rather than ending the stream.
+pub fn replay_stream(
+    context: Context,
+    interval: Duration,
+    min_floor: Floor,
+    max_floor: Floor,
+    start: StreamStart,
+    filter: impl Fn(&Match) -> bool + Send + 'static,
+) -> impl Stream<Item = Match> {
+    async_stream::stream! {
+        let mut seen: BTreeSet<Match> = BTreeSet::new();
+        let mut first_tick = true;
+
+        loop {
+            let pages = if first_tick {
+                match start {
+                    StreamStart::Backfill(pages) => pages.min(MAX_REPLAY_PAGES),
+                    StreamStart::SkipExisting => STREAM_POLL_PAGES,
                 }
+            } else {
+                STREAM_POLL_PAGES
             };
-
-            // Construct the match
-            let match_data = Match {
-                floor: Floor::from_u8(floor)?,
-                timestamp: match NaiveDateTime::parse_from_str(&time, "%Y-%m-%d %H:%M:%S") {
-                    Ok(t) => DateTime::<Utc>::from_utc(t, Utc),
-                    Err(_) => {
-                        return Err(Error::UnexpectedResponse(format!(
-                            "Could not parse datetime {}",
-                            &time
-                        )))
+            let suppress_emit = first_tick && matches!(start, StreamStart::SkipExisting);
+
+            if let Ok(polled) = get_replays(&context, pages, min_floor, max_floor).await {
+                let mut fresh: Vec<Match> = polled
+                    .filter(&filter)
+                    .filter(|m| !seen.contains(m))
+                    .collect();
+                fresh.sort();
+
+                for m in fresh {
+                    if !suppress_emit {
+                        yield m.clone();
                     }
-                },
-                players: (
-                    Player {
-                        id: u64::from_str_radix(&p1_id, 10).map_err(|_| {
-                            Error::UnexpectedResponse(format!(
-                                "Could not parse u64 id from {}",
-                                p1_id
-                            ))
-                        })?,
-                        name: p1_name.to_string(),
-                        character: Character::from_u8(p1_char)?,
-                    },
-                    Player {
-                        id: u64::from_str_radix(&p2_id, 10).map_err(|_| {
-                            Error::UnexpectedResponse(format!(
-                                "Could not parse u64 id from {}",
-                                p2_id
-                            ))
-                        })?,
-                        name: p2_name.to_string(),
-                        character: Character::from_u8(p2_char)?,
-                    },
-                ),
-                winner: match winner {
-                    1 => Winner::Player1,
-                    2 => Winner::Player2,
-                    _ => {
-                        return Err(Error::UnexpectedResponse(format!(
-                            "Could not parse winner {}",
-                            winner
-                        )))
+                    seen.insert(m);
+                }
+
+                while seen.len() > STREAM_SEEN_CAPACITY {
+                    if let Some(oldest) = seen.iter().next().cloned() {
+                        seen.remove(&oldest);
                     }
-                },
-            };
+                }
+
+                first_tick = false;
+            }
 
-            // Insert it into the set
-            matches.insert(match_data);
+            tokio::time::sleep(interval).await;
         }
     }
-    Ok(matches.into_iter())
 }
 
 async fn userid_from_steamid(context: &Context, steamid: &str) -> Result<String> {
     let request_url = format!("{}/{}.json", context.utils_base_url, steamid);
-    let response = reqwest::get(request_url).await?;
-    let d: Value = serde_json::from_str(&response.text().await?)?;
+    let response = send_with_retry(context, context.client.get(&request_url)).await?;
+    let d: serde_json::Value = serde_json::from_str(&response.text().await?)?;
     match d.get("UserID") {
         Some(s) => Ok(String::from(
             s.as_str()
@@ -338,23 +603,61 @@ async fn userid_from_steamid(context: &Context, steamid: &str) -> Result<String>
     }
 }
 
-/// Receive user data from a steamid
-pub async fn user_from_steamid(context: &Context, steamid: &str) -> Result<User> {
+// Typed view of the known keys in the `statistics/get` response. Every stat field is optional
+// because the server omits a category entirely rather than sending zeroes when it has nothing to
+// report for it (e.g. a brand new account has no `CelestialPlayNum`/`CelestialWinNum` at all).
+#[derive(Debug, Deserialize)]
+struct StatisticsPayload {
+    #[serde(rename = "NickName")]
+    nick_name: String,
+    #[serde(rename = "PublicComment")]
+    public_comment: String,
+    #[serde(rename = "Floor", default)]
+    floor: Option<u8>,
+    #[serde(rename = "TotalPlayNum", default)]
+    total_play_num: Option<u64>,
+    #[serde(rename = "TotalWinNum", default)]
+    total_win_num: Option<u64>,
+    #[serde(rename = "CelestialPlayNum", default)]
+    celestial_play_num: Option<u64>,
+    #[serde(rename = "CelestialWinNum", default)]
+    celestial_win_num: Option<u64>,
+    // Indexed the same way as `Character::from_u8`.
+    #[serde(rename = "CharacterPlayNum", default)]
+    character_play_num: Option<Vec<u64>>,
+    #[serde(rename = "CharacterWinNum", default)]
+    character_win_num: Option<Vec<u64>>,
+}
+
+/// Indicates which stat categories were actually present in the raw `statistics/get` payload, so
+/// callers can tell a player with zero recorded games in a category apart from the server having
+/// omitted that category entirely.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatsAvailability {
+    pub floor: bool,
+    pub stats: bool,
+    pub celestial_stats: bool,
+    pub char_stats: bool,
+}
+
+/// Receive user data from a steamid, together with a [`StatsAvailability`] describing which of
+/// the populated stat fields actually came from the server as opposed to being filled with a
+/// default (zero counts, or `Floor::Celestial` for the floor).
+pub async fn user_from_steamid(
+    context: &Context,
+    steamid: &str,
+) -> Result<(User, StatsAvailability)> {
     // Get the user id from the steamid
     let id = userid_from_steamid(context, steamid).await?;
 
     // Construct the request with token and appropriate AOB
     let request_url = format!("{}/api/statistics/get", context.base_url);
-    let client = reqwest::Client::new();
     let query = format!(
         "9295B2323131303237313133313233303038333834AD3631393064363236383739373702A5302E302E380396B2{}070101FFFFFF",
         id.encode_hex::<String>()
     );
-    let response = client
-        .post(request_url)
-        .form(&[("data", query)])
-        .send()
-        .await?;
+    let request = context.client.post(request_url).form(&[("data", query)]);
+    let response = send_with_retry(context, request).await?;
 
     // Remove invalid unicode stuff before the actual json body
     let content = &response.text().await?;
@@ -362,50 +665,246 @@ pub async fn user_from_steamid(context: &Context, steamid: &str) -> Result<User>
         static ref RE: Regex = Regex::new(r"[^\{]*\{").expect("Could not compile regex");
     }
     let content = RE.replacen(content, 1, "{");
-    let v: Value = serde_json::from_str(&content)?;
+    let payload: StatisticsPayload = serde_json::from_str(&content)?;
+
+    let availability = StatsAvailability {
+        floor: payload.floor.is_some(),
+        stats: payload.total_play_num.is_some() && payload.total_win_num.is_some(),
+        celestial_stats: payload.celestial_play_num.is_some()
+            && payload.celestial_win_num.is_some(),
+        char_stats: payload.character_play_num.is_some() && payload.character_win_num.is_some(),
+    };
+
+    let char_stats = match (&payload.character_play_num, &payload.character_win_num) {
+        (Some(play), Some(wins)) => play
+            .iter()
+            .zip(wins.iter())
+            .enumerate()
+            .filter_map(|(i, (&total, &wins))| {
+                Character::from_u8(i as u8)
+                    .ok()
+                    .map(|c| (c, MatchStats { total, wins }))
+            })
+            .collect(),
+        _ => HashMap::new(),
+    };
 
     // Assemble the user object
-    Ok(User {
-        id,
-        name: String::from(
-            v.get("NickName")
-                .ok_or(Error::UnexpectedResponse("Could not parse username".into()))?
-                .as_str()
-                .ok_or(Error::UnexpectedResponse("Could not parse username".into()))?,
-        ),
-        comment: String::from(
-            v.get("PublicComment")
-                .ok_or(Error::UnexpectedResponse(
-                    "Could not parse profile comment".into(),
-                ))?
-                .as_str()
-                .ok_or(Error::UnexpectedResponse(
-                    "Could not parse profile comment".into(),
-                ))?,
-        ),
-        floor: Floor::Celestial,
-        stats: MatchStats { total: 0, wins: 0 },
-        celestial_stats: MatchStats { total: 0, wins: 0 },
-        char_stats: HashMap::new(),
-    })
+    Ok((
+        User {
+            id,
+            name: payload.nick_name,
+            comment: payload.public_comment,
+            floor: match payload.floor {
+                Some(f) => Floor::from_u8(f)?,
+                None => Floor::Celestial,
+            },
+            stats: MatchStats {
+                total: payload.total_play_num.unwrap_or(0),
+                wins: payload.total_win_num.unwrap_or(0),
+            },
+            celestial_stats: MatchStats {
+                total: payload.celestial_play_num.unwrap_or(0),
+                wins: payload.celestial_win_num.unwrap_or(0),
+            },
+            char_stats,
+        },
+        availability,
+    ))
 }
 
-// Helper function for debugging
-fn show_buf<B: AsRef<[u8]>>(buf: B) -> String {
-    use std::ascii::escape_default;
-    String::from_utf8(
-        buf.as_ref()
-            .iter()
-            .map(|b| escape_default(*b))
-            .flatten()
-            .collect(),
-    )
-    .unwrap()
+/// Render an iterator of [`Match`] as an RSS 2.0 feed, one `<item>` per match. Pass `player_id`
+/// to restrict the feed to matches involving that player; pass `None` to include everything
+/// handed in, e.g. the output of [`get_replays`] filtered to a single floor or character. Gated
+/// behind the `rss` feature so `quick-xml` isn't pulled into the default build.
+#[cfg(feature = "rss")]
+pub fn matches_to_rss<'a>(
+    matches: impl IntoIterator<Item = &'a Match>,
+    channel_title: &str,
+    channel_link: &str,
+    player_id: Option<u64>,
+) -> Result<String> {
+    use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, Event};
+    use quick_xml::Writer;
+    use std::io::Cursor;
+
+    let xml_err = |e: quick_xml::Error| Error::UnexpectedResponse(format!("Could not write RSS feed: {}", e));
+
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+    writer
+        .write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))
+        .map_err(xml_err)?;
+
+    writer
+        .write_event(Event::Start(
+            BytesStart::new("rss").with_attributes([("version", "2.0")]),
+        ))
+        .map_err(xml_err)?;
+    writer
+        .write_event(Event::Start(BytesStart::new("channel")))
+        .map_err(xml_err)?;
+    write_text_element(&mut writer, "title", channel_title)?;
+    write_text_element(&mut writer, "link", channel_link)?;
+    write_text_element(
+        &mut writer,
+        "description",
+        &format!("Recent Guilty Gear Strive matches for {}", channel_title),
+    )?;
+
+    let involves_player = |m: &Match| match player_id {
+        Some(id) => m.players().0.id() == id || m.players().1.id() == id,
+        None => true,
+    };
+
+    for (i, m) in matches
+        .into_iter()
+        .filter(|m| involves_player(m))
+        .enumerate()
+    {
+        let (p1, p2) = m.players();
+
+        writer
+            .write_event(Event::Start(BytesStart::new("item")))
+            .map_err(xml_err)?;
+        write_text_element(
+            &mut writer,
+            "title",
+            &format!(
+                "{} ({:?}) vs {} ({:?})",
+                p1.name(),
+                p1.character(),
+                p2.name(),
+                p2.character()
+            ),
+        )?;
+        let winner_name = match m.winner() {
+            Winner::Player1 => p1.name(),
+            Winner::Player2 => p2.name(),
+        };
+        write_text_element(
+            &mut writer,
+            "description",
+            &format!("Floor {:?}, winner: {}", m.floor(), winner_name),
+        )?;
+        write_text_element(&mut writer, "pubDate", &m.timestamp().to_rfc2822())?;
+        write_guid_element(
+            &mut writer,
+            &format!(
+                "{}-{}-{}-{}",
+                p1.id(),
+                p2.id(),
+                m.timestamp().timestamp(),
+                i
+            ),
+        )?;
+        writer
+            .write_event(Event::End(BytesEnd::new("item")))
+            .map_err(xml_err)?;
+    }
+
+    writer
+        .write_event(Event::End(BytesEnd::new("channel")))
+        .map_err(xml_err)?;
+    writer
+        .write_event(Event::End(BytesEnd::new("rss")))
+        .map_err(xml_err)?;
+
+    String::from_utf8(writer.into_inner().into_inner())
+        .map_err(|e| Error::UnexpectedResponse(format!("Generated RSS feed was not valid UTF-8: {}", e)))
+}
+
+#[cfg(feature = "rss")]
+fn write_text_element<W: std::io::Write>(
+    writer: &mut quick_xml::Writer<W>,
+    tag: &str,
+    text: &str,
+) -> Result<()> {
+    use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+
+    let xml_err = |e: quick_xml::Error| Error::UnexpectedResponse(format!("Could not write RSS feed: {}", e));
+
+    writer
+        .write_event(Event::Start(BytesStart::new(tag)))
+        .map_err(xml_err)?;
+    writer
+        .write_event(Event::Text(BytesText::new(text)))
+        .map_err(xml_err)?;
+    writer
+        .write_event(Event::End(BytesEnd::new(tag)))
+        .map_err(xml_err)?;
+    Ok(())
+}
+
+/// Writes a `<guid isPermaLink="false">` element. `guid` values aren't URLs, so per the RSS 2.0
+/// spec we mark them as such explicitly — otherwise compliant feed readers default to treating
+/// the value as a permalink.
+#[cfg(feature = "rss")]
+fn write_guid_element<W: std::io::Write>(writer: &mut quick_xml::Writer<W>, guid: &str) -> Result<()> {
+    use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+
+    let xml_err = |e: quick_xml::Error| Error::UnexpectedResponse(format!("Could not write RSS feed: {}", e));
+
+    writer
+        .write_event(Event::Start(
+            BytesStart::new("guid").with_attributes([("isPermaLink", "false")]),
+        ))
+        .map_err(xml_err)?;
+    writer
+        .write_event(Event::Text(BytesText::new(guid)))
+        .map_err(xml_err)?;
+    writer
+        .write_event(Event::End(BytesEnd::new("guid")))
+        .map_err(xml_err)?;
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn encode_replay_page_segment_matches_legacy_hex_template() {
+        let page = 3u8;
+        let segment = encode_replay_page_segment(page, Floor::F1, Floor::Celestial).unwrap();
+
+        // What the hand-rolled `format!("...9401CC{}0A9AFF00{}{}90FFFF000001", ...)` template
+        // this replaced would have produced for the same inputs.
+        let expected = format!(
+            "9401CC{:02X}0A9AFF00{:02X}{:02X}90FFFF000001",
+            page,
+            Floor::F1 as u8,
+            Floor::Celestial as u8,
+        );
+        assert_eq!(segment.encode_hex_upper::<String>(), expected);
+    }
+
+    #[test]
+    fn parse_replay_record_handles_missing_online_id() {
+        // `header`'s last 3 fields are [floor, p1_char, p2_char]; `player2_and_result` here has
+        // exactly the 4 minimum fields (id, name, winner, timestamp), i.e. no online id in the
+        // middle, which is the edge case `parse_replay_record` is meant to tolerate.
+        let header = Value::Array(vec![Value::from(0u8), Value::from(0u8), Value::from(0u8)]);
+        let player1 = Value::Array(vec![
+            Value::from("111111111111111111"),
+            Value::from("Player One"),
+        ]);
+        let player2_and_result = Value::Array(vec![
+            Value::from("222222222222222222"),
+            Value::from("Player Two"),
+            Value::from(2u8),
+            Value::from("2024-01-02 03:04:05"),
+        ]);
+        let record = Value::Array(vec![header, player1, player2_and_result]);
+
+        let m = parse_replay_record(&record).unwrap();
+        let (p1, p2) = m.players();
+        assert_eq!(p1.id(), 111111111111111111);
+        assert_eq!(p1.name(), "Player One");
+        assert_eq!(p2.id(), 222222222222222222);
+        assert_eq!(p2.name(), "Player Two");
+        assert!(matches!(m.winner(), Winner::Player2));
+    }
+
     #[tokio::test]
     async fn get_userid() {
         let ctx = Context::new();
@@ -418,7 +917,7 @@ mod tests {
     #[tokio::test]
     async fn get_user_stats() {
         let ctx = Context::new();
-        let user = user_from_steamid(&ctx, "76561198045733267").await.unwrap();
+        let (user, _availability) = user_from_steamid(&ctx, "76561198045733267").await.unwrap();
         assert_eq!(user.name, "enemy fungus");
     }
 